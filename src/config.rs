@@ -0,0 +1,94 @@
+//! TOML configuration for non-interactive / batch runs, so the serial/patch
+//! workflow can be driven from CI or a Docker-OSX build step instead of the
+//! interactive stdin prompts in [`main`](crate).
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::kernel_patches::{self, PatchCategory};
+
+fn default_keep_existing() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RunConfig {
+    /// Overrides the `SystemProductName` used to pick serial model codes.
+    pub system_product_name: Option<String>,
+    /// Explicit serial number; takes precedence over generation. Must be set
+    /// together with `mlb`, or not at all.
+    pub serial_number: Option<String>,
+    /// Explicit MLB (board serial); takes precedence over generation. Must
+    /// be set together with `serial_number`, or not at all.
+    pub mlb: Option<String>,
+    /// Explicit SystemUUID; takes precedence over generation.
+    pub uuid: Option<String>,
+    /// Explicit ROM, as a 24-character hex string; takes precedence over generation.
+    pub rom: Option<String>,
+    /// Kernel patch categories to apply: "vm-detection-bypass", "board-id-spoof".
+    /// A category with no registered patches (as "board-id-spoof" has none)
+    /// is rejected with an error.
+    #[serde(default)]
+    pub patch_categories: Vec<String>,
+    /// Keep the existing serial/MLB/UUID/ROM values instead of regenerating them.
+    #[serde(default = "default_keep_existing")]
+    pub keep_existing: bool,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            system_product_name: None,
+            serial_number: None,
+            mlb: None,
+            uuid: None,
+            rom: None,
+            patch_categories: Vec::new(),
+            keep_existing: default_keep_existing(),
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    pub fn resolved_patch_categories(&self) -> Result<Vec<PatchCategory>> {
+        self.patch_categories
+            .iter()
+            .map(|name| {
+                let category = match name.as_str() {
+                    "vm-detection-bypass" => PatchCategory::VmDetectionBypass,
+                    "board-id-spoof" => PatchCategory::BoardIdSpoof,
+                    other => bail!("Unknown patch category {other:?}"),
+                };
+                if !kernel_patches::category_has_patches(category) {
+                    bail!("Patch category {name:?} has no patches registered yet");
+                }
+                Ok(category)
+            })
+            .collect()
+    }
+
+    /// Parses the configured ROM hex string, which must decode to exactly 12
+    /// bytes (the same length as a ROM generated in `main`).
+    pub fn parsed_rom(&self) -> Result<Option<[u8; 12]>> {
+        let Some(rom) = &self.rom else {
+            return Ok(None);
+        };
+        let bytes = hex::decode(rom).with_context(|| format!("Invalid rom hex {rom:?} in config"))?;
+        if bytes.len() != 12 {
+            bail!("rom must be exactly 12 bytes (24 hex chars), got {}", bytes.len());
+        }
+        let mut array = [0u8; 12];
+        array.copy_from_slice(&bytes);
+        Ok(Some(array))
+    }
+}
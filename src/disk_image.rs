@@ -0,0 +1,87 @@
+//! Disk-backend abstraction so the serial/patch logic in [`main`](crate) can
+//! operate on a qcow2 image, a raw `.img` file, or a real block device
+//! interchangeably, the way coreos-installer's blockdev layer picks a
+//! backend by sniffing the container rather than trusting the file extension.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::qcow2::Qcow2;
+
+/// Magic bytes at the start of a qcow2 image ("QFI\xfb").
+const QCOW2_MAGIC: [u8; 4] = [0x51, 0x46, 0x49, 0xfb];
+
+/// A disk container backing the OpenCore EFI partition: either a qcow2
+/// image, or a raw `.img` file / block device accessed directly.
+pub enum DiskImage {
+    Qcow2(Qcow2),
+    Raw(File),
+}
+
+impl DiskImage {
+    /// Opens `path`, detecting qcow2 vs. raw by magic bytes rather than file
+    /// extension, so a raw `.img` or a `/dev/sdX` block device both work.
+    pub fn open(path: &Path, dry_run: bool) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        let mut probe = File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        probe.read_exact(&mut magic).context("Failed to read container magic bytes")?;
+
+        if magic == QCOW2_MAGIC {
+            Ok(Self::Qcow2(Qcow2::new(path, dry_run)?))
+        } else {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(!dry_run)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            Ok(Self::Raw(file))
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        match self {
+            Self::Qcow2(qcow2) => qcow2.flush(),
+            Self::Raw(file) => file.flush().context("Failed to flush raw image"),
+        }
+    }
+}
+
+impl Read for DiskImage {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Qcow2(qcow2) => Read::read(qcow2, buf),
+            Self::Raw(file) => file.read(buf),
+        }
+    }
+}
+
+impl Write for DiskImage {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Qcow2(qcow2) => Write::write(qcow2, buf),
+            Self::Raw(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Qcow2(qcow2) => Write::flush(qcow2),
+            Self::Raw(file) => file.flush(),
+        }
+    }
+}
+
+impl Seek for DiskImage {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::Qcow2(qcow2) => Seek::seek(qcow2, pos),
+            Self::Raw(file) => file.seek(pos),
+        }
+    }
+}
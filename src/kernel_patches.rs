@@ -0,0 +1,139 @@
+//! Static registry of OpenCore `Kernel -> Patch` blobs, modeled on the
+//! find/replace patchers used by Clover and Chameleon. Each [`KernelPatch`]
+//! is self-contained and tagged with the Darwin kernel range it applies to,
+//! so callers pick a target kernel and a set of [`PatchCategory`]s instead of
+//! calling one hardcoded method per quirk.
+
+/// Grouping used to select which patches from [`ALL_PATCHES`] apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchCategory {
+    /// Hides `kern.hv_vmm_present` so macOS doesn't refuse Apple ID / iMessage
+    /// services when it detects it's running in a VM.
+    VmDetectionBypass,
+    /// Spoofs board-id strings that macOS uses to gate model-specific features.
+    BoardIdSpoof,
+}
+
+/// A Darwin kernel version such as "24.0.0", parsed into its numeric parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KernelVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().unwrap_or("0").parse().ok()?;
+        let patch = parts.next().unwrap_or("0").parse().ok()?;
+        Some(Self { major, minor, patch })
+    }
+
+    /// Whether `self` falls within `[min, max]`, where an empty bound string
+    /// means unbounded on that side (matching OpenCore's own convention).
+    fn within(&self, min: &str, max: &str) -> bool {
+        if !min.is_empty() {
+            match KernelVersion::parse(min) {
+                Some(min) if *self < min => return false,
+                _ => {}
+            }
+        }
+        if !max.is_empty() {
+            match KernelVersion::parse(max) {
+                Some(max) if *self > max => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+/// One OpenCore `Kernel -> Patch` entry. Byte slices use OpenCore's mask
+/// convention: a byte at index `i` matches when
+/// `(found[i] & mask[i]) == (find[i] & mask[i])`, and an empty mask means an
+/// exact match is required.
+#[derive(Debug, Clone, Copy)]
+pub struct KernelPatch {
+    pub comment: &'static str,
+    pub identifier: &'static str,
+    pub arch: &'static str,
+    pub find: &'static [u8],
+    pub replace: &'static [u8],
+    pub mask: &'static [u8],
+    pub replace_mask: &'static [u8],
+    pub count: u64,
+    pub skip: u64,
+    pub min_kernel: &'static str,
+    pub max_kernel: &'static str,
+    pub category: PatchCategory,
+}
+
+/// Known patches, grouped by purpose. New quirks are added here rather than
+/// as new `MacPlist` methods.
+pub static ALL_PATCHES: &[KernelPatch] = &[
+    KernelPatch {
+        comment: "Disable VM detection (kern.hv_vmm_present -> hibernatecount) for Sequoia",
+        identifier: "kernel",
+        arch: "x86_64",
+        find: b"hibernatehidready\0hibernatecount\0",
+        replace: b"hibernatehidready\0hv_vmm_present\0",
+        mask: &[],
+        replace_mask: &[],
+        count: 1,
+        skip: 0,
+        min_kernel: "24.0.0",
+        max_kernel: "",
+        category: PatchCategory::VmDetectionBypass,
+    },
+    KernelPatch {
+        comment: "Disable VM detection (hibernatecount -> hv_vmm_present) for Sequoia",
+        identifier: "kernel",
+        arch: "x86_64",
+        find: b"boot session UUID\0hv_vmm_present\0",
+        replace: b"boot session UUID\0hibernatecount\0",
+        mask: &[],
+        replace_mask: &[],
+        count: 1,
+        skip: 0,
+        min_kernel: "24.0.0",
+        max_kernel: "",
+        category: PatchCategory::VmDetectionBypass,
+    },
+];
+
+/// Byte-for-byte match honoring the mask convention described on
+/// [`KernelPatch`]. An empty mask requires an exact match; a non-empty mask
+/// must be the same length as `find`.
+pub fn mask_matches(found: &[u8], find: &[u8], mask: &[u8]) -> bool {
+    if found.len() != find.len() {
+        return false;
+    }
+    if !mask.is_empty() && mask.len() != find.len() {
+        return false;
+    }
+    found.iter().zip(find.iter()).enumerate().all(|(i, (f, w))| {
+        if mask.is_empty() {
+            f == w
+        } else {
+            (f & mask[i]) == (w & mask[i])
+        }
+    })
+}
+
+/// Whether `category` has at least one entry in [`ALL_PATCHES`]. Used to
+/// reject a requested category that would otherwise silently apply nothing.
+pub fn category_has_patches(category: PatchCategory) -> bool {
+    ALL_PATCHES.iter().any(|p| p.category == category)
+}
+
+/// Patches applicable to `target_kernel` from the given `categories`, in
+/// registry order.
+pub fn patches_for(target_kernel: KernelVersion, categories: &[PatchCategory]) -> Vec<&'static KernelPatch> {
+    ALL_PATCHES
+        .iter()
+        .filter(|p| categories.contains(&p.category))
+        .filter(|p| target_kernel.within(p.min_kernel, p.max_kernel))
+        .collect()
+}
@@ -5,7 +5,10 @@
 #[allow(improper_ctypes)]
 mod modelinfo;
 
+mod config;
+mod disk_image;
 mod io_subset;
+mod kernel_patches;
 mod oui;
 mod plist_data;
 mod qcow2;
@@ -18,12 +21,14 @@ use std::{
 
 use anyhow::{bail, Context, Result};
 use clap::Parser;
+use config::RunConfig;
 use fatfs::FileSystem;
 use gpt::{disk::LogicalBlockSize, GptConfig};
+use disk_image::DiskImage;
 use io_subset::IoSubset;
+use kernel_patches::{KernelVersion, PatchCategory};
 use oui::APPLE_OUIS;
 use plist_data::MacPlist;
-use qcow2::Qcow2;
 use rand::seq::IndexedRandom;
 use uuid::Uuid;
 
@@ -35,8 +40,19 @@ fn main() -> Result<()> {
         .filter_level(args.verbose.log_level_filter())
         .init();
 
-    let mut qcow2 = Qcow2::new(&args.bootloader, args.dry_run)?;
-    let mut first_partition = first_partition_subset(&mut qcow2)?;
+    let config = args
+        .config
+        .as_deref()
+        .map(RunConfig::load)
+        .transpose()?
+        .unwrap_or_default();
+    if config.serial_number.is_some() != config.mlb.is_some() {
+        bail!("Config must set both `serial-number` and `mlb` together, or neither");
+    }
+    let non_interactive = args.yes || args.config.is_some();
+
+    let mut disk = DiskImage::open(&args.bootloader, args.dry_run)?;
+    let mut first_partition = first_partition_subset(&mut disk, args.partition.as_deref())?;
 
     let fs = FileSystem::new(&mut first_partition, fatfs::FsOptions::new())
         .context("Failed to open FAT32 filesystem")?;
@@ -48,6 +64,8 @@ fn main() -> Result<()> {
 
     let mut plist: MacPlist = plist::from_reader(&mut conf_plist)?;
 
+    let explicit_serial = config.serial_number.as_ref().zip(config.mlb.as_ref());
+
     let mut needs_update = false;
 
     // Check if valid serials already exist
@@ -56,8 +74,16 @@ fn main() -> Result<()> {
         println!("  Serial Number: {}", plist.get_serial_number());
         println!("  MLB: {}", plist.get_mlb());
         println!();
-        
-        if !args.dry_run {
+
+        if explicit_serial.is_some() {
+            needs_update = true;
+        } else if non_interactive {
+            if config.keep_existing {
+                println!("Keeping existing serial numbers.");
+            } else {
+                needs_update = true;
+            }
+        } else if !args.dry_run {
             print!("Do you want to regenerate new serial numbers? (y/N) ");
             stdout().flush()?;
             let mut buffer = String::new();
@@ -72,8 +98,17 @@ fn main() -> Result<()> {
         needs_update = true;
     }
 
-    let serial = if needs_update {
-        serial::find_desired(plist.get_product_name())?
+    let serial = if let Some((serial_number, mlb)) = explicit_serial {
+        serial::Serial {
+            serial_number: serial_number.clone(),
+            board_serial: mlb.clone(),
+        }
+    } else if needs_update {
+        let product_name = config
+            .system_product_name
+            .as_deref()
+            .unwrap_or_else(|| plist.get_product_name());
+        serial::find_desired(product_name)?
     } else {
         serial::Serial {
             serial_number: plist.get_serial_number().to_string(),
@@ -81,14 +116,24 @@ fn main() -> Result<()> {
         }
     };
 
-    let uuid = if needs_update {
+    let existing_uuid = Uuid::parse_str(plist.get_system_uuid()).ok();
+    let existing_rom = plist
+        .get_rom()
+        .map(|rom| rom.to_vec())
+        .filter(|rom| !rom.iter().all(|byte| *byte == 0));
+
+    let uuid = if let Some(explicit) = &config.uuid {
+        Uuid::parse_str(explicit).with_context(|| format!("Invalid uuid {explicit:?} in config"))?
+    } else if needs_update || existing_uuid.is_none() {
         Uuid::new_v4()
     } else {
-        // Keep existing UUID
-        Uuid::new_v4() // We'll keep this for now; ideally we'd parse the existing one
+        // Keep the existing UUID so iCloud/Apple ID sessions stay stable.
+        existing_uuid.unwrap()
     };
 
-    let rom = if needs_update {
+    let rom = if let Some(explicit) = config.parsed_rom()? {
+        explicit.to_vec()
+    } else if needs_update || existing_rom.is_none() {
         let mut rom = [0; 12];
         let mut rng = rand::rng();
 
@@ -106,48 +151,66 @@ fn main() -> Result<()> {
                 .context("Hex digits couldn't be generated")?;
         }
 
-        rom
+        rom.to_vec()
     } else {
-        [0; 12] // Keep existing ROM
+        // Keep the existing ROM (whatever length it was stored as) so
+        // iCloud/Apple ID sessions stay stable.
+        existing_rom.clone().unwrap()
     };
 
+    // Whether the machine identity (serial/MLB/UUID/ROM) actually changed,
+    // e.g. because it needed regeneration or because it was missing/invalid.
+    let identity_changed = needs_update || Some(uuid) != existing_uuid || Some(rom.clone()) != existing_rom;
+
     // Check and add Sequoia patches if requested
+    let sequoia = KernelVersion::parse("24.0.0").context("Failed to parse Sequoia kernel version")?;
+    let configured_categories = config.resolved_patch_categories()?;
+    let patch_categories = if configured_categories.is_empty() {
+        vec![PatchCategory::VmDetectionBypass]
+    } else {
+        configured_categories
+    };
+    let categories_requested = args.add_sequoia_patches
+        || args.force_sequoia_patches
+        || !config.patch_categories.is_empty();
+
     let mut patches_added = false;
-    if args.add_sequoia_patches || args.force_sequoia_patches {
-        if !plist.has_sequoia_patches() || args.force_sequoia_patches {
+    if categories_requested {
+        if !plist.has_patches(&sequoia, &patch_categories) || args.force_sequoia_patches {
             println!();
             println!("Adding macOS Sequoia kernel patches for VM detection bypass...");
-            plist.add_sequoia_kernel_patches();
+            plist.apply_patches(&sequoia, &patch_categories);
             patches_added = true;
         } else {
             println!();
             println!("Sequoia kernel patches already present.");
         }
-    } else if !plist.has_sequoia_patches() {
+    } else if !plist.has_patches(&sequoia, &patch_categories) {
         println!();
         println!("Note: Sequoia kernel patches not found in config.plist.");
         println!("For macOS Sequoia 15.7.1+, you need kernel patches to enable Apple ID login.");
-        print!("Would you like to add them now? (Y/n) ");
-        stdout().flush()?;
-        let mut buffer = String::new();
-        stdin().read_line(&mut buffer)?;
-        let answer = buffer.trim();
-        if answer.is_empty() || answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
-            plist.add_sequoia_kernel_patches();
-            patches_added = true;
+        if non_interactive {
+            println!("Skipping in non-interactive mode (set patch-categories in the config or pass --add-sequoia-patches).");
+        } else {
+            print!("Would you like to add them now? (Y/n) ");
+            stdout().flush()?;
+            let mut buffer = String::new();
+            stdin().read_line(&mut buffer)?;
+            let answer = buffer.trim();
+            if answer.is_empty() || answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
+                plist.apply_patches(&sequoia, &patch_categories);
+                patches_added = true;
+            }
         }
     }
 
     if args.dry_run {
         println!();
-        if needs_update {
+        if identity_changed {
             println!("Would set serial number to {}", serial.serial_number);
             println!("Would set MLB to {}", serial.board_serial);
             println!("Would set UUID to {}", uuid);
-            println!(
-                "Would set ROM to {:?}",
-                std::str::from_utf8(&rom).context("ROM should always be valid UTF-8")?
-            );
+            println!("Would set ROM to {}", hex::encode(&rom));
         }
         if patches_added {
             println!("Would add Sequoia kernel patches");
@@ -156,8 +219,8 @@ fn main() -> Result<()> {
     }
 
     // Only update if changes were made
-    if needs_update || patches_added {
-        if needs_update {
+    if identity_changed || patches_added {
+        if identity_changed {
             plist.set_serial_number(serial.serial_number);
             plist.set_mlb(serial.board_serial);
             plist.set_uuid(uuid);
@@ -175,7 +238,7 @@ fn main() -> Result<()> {
         drop(conf_plist);
         fs.unmount()?;
         first_partition.flush()?;
-        qcow2.flush()?;
+        disk.flush()?;
 
         println!();
         println!("✓ Configuration updated successfully!");
@@ -197,26 +260,85 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn first_partition_subset(mut qcow2: &mut Qcow2) -> Result<IoSubset<&mut Qcow2>> {
-    let disk = GptConfig::new().open_from_device(&mut qcow2)?;
+/// Resolves the OpenCore EFI partition. If `selector` is given it's matched
+/// against the GPT partition name (case-insensitively) or, if it parses as a
+/// number, against the 1-based partition index. With no selector, every
+/// partition is scanned in index order for the first one that actually
+/// contains `EFI/OC/config.plist`, since the ESP isn't always partition 1.
+fn first_partition_subset<'a>(
+    disk_image: &'a mut DiskImage,
+    selector: Option<&str>,
+) -> Result<IoSubset<&'a mut DiskImage>> {
+    let gpt_disk = GptConfig::new().open_from_device(&mut disk_image)?;
+    let partitions = gpt_disk.partitions();
 
-    let partitions = disk.partitions();
-    let partition = partitions.get(&1).context("Failed to get partition")?;
+    let mut candidates: Vec<(u32, u64, u64)> = partitions
+        .iter()
+        .map(|(index, partition)| {
+            let start = partition.bytes_start(LogicalBlockSize::Lb512)?;
+            let end = start + partition.bytes_len(LogicalBlockSize::Lb512)?;
+            Ok((*index, start, end))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    candidates.sort_by_key(|(index, _, _)| *index);
 
-    let start = partition.bytes_start(LogicalBlockSize::Lb512)?;
-    let end = start + partition.bytes_len(LogicalBlockSize::Lb512)?;
+    if let Some(selector) = selector {
+        let (start, end) = if let Ok(index) = selector.parse::<u32>() {
+            let partition = partitions
+                .get(&index)
+                .with_context(|| format!("No partition with index {index}"))?;
+            let start = partition.bytes_start(LogicalBlockSize::Lb512)?;
+            (start, start + partition.bytes_len(LogicalBlockSize::Lb512)?)
+        } else {
+            let partition = partitions
+                .values()
+                .find(|p| p.name.eq_ignore_ascii_case(selector))
+                .with_context(|| format!("No partition named {selector:?}"))?;
+            let start = partition.bytes_start(LogicalBlockSize::Lb512)?;
+            (start, start + partition.bytes_len(LogicalBlockSize::Lb512)?)
+        };
+        return Ok(IoSubset::new(disk_image, start, end));
+    }
+
+    for (_, start, end) in &candidates {
+        let mut subset = IoSubset::new(&mut *disk_image, *start, *end);
+        let has_config = FileSystem::new(&mut subset, fatfs::FsOptions::new())
+            .ok()
+            .map(|fs| fs.root_dir().open_file("EFI/OC/config.plist").is_ok())
+            .unwrap_or(false);
+        if has_config {
+            return Ok(IoSubset::new(disk_image, *start, *end));
+        }
+    }
 
-    Ok(IoSubset::new(qcow2, start, end))
+    bail!("No partition containing EFI/OC/config.plist was found")
 }
 
 #[derive(Parser)]
 struct Args {
-    #[clap(long, help = "Path to the bootloader ('OpenCore.qcow2')")]
+    #[clap(long, help = "Path to the bootloader image (qcow2, raw .img, or a block device)")]
     bootloader: PathBuf,
-    
+
+    #[clap(
+        long,
+        help = "EFI partition to use, by GPT label or 1-based index (default: auto-detect by scanning for EFI/OC/config.plist)"
+    )]
+    partition: Option<String>,
+
+    #[clap(long, help = "TOML config file driving a non-interactive run (implies --yes)")]
+    config: Option<PathBuf>,
+
+    #[clap(
+        short,
+        long,
+        alias = "non-interactive",
+        help = "Suppress every prompt and take defaults (or config values)"
+    )]
+    yes: bool,
+
     #[clap(short, long, help = "Don't commit changes to disk")]
     dry_run: bool,
-    
+
     #[clap(short = 'f', long, help = "Force regeneration of serial numbers even if valid ones exist")]
     force_regenerate: bool,
     
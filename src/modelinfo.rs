@@ -0,0 +1,54 @@
+//! Model/configuration-code table, keyed by `SystemProductName`. This is the
+//! same kind of lookup table Acidanthera's `macserial` ships (there compiled
+//! from Apple's SMBIOS model list); only a representative subset of models is
+//! included here, not the full catalog.
+
+pub struct ModelInfo {
+    pub product_name: &'static str,
+    /// The 3- or 4-character model/configuration code Apple embeds as the
+    /// suffix of a serial number for this product.
+    pub model_code: &'static str,
+}
+
+pub static MODEL_INFO: &[ModelInfo] = &[
+    ModelInfo { product_name: "iMac18,3", model_code: "00KQ" },
+    ModelInfo { product_name: "iMac19,1", model_code: "0079" },
+    ModelInfo { product_name: "iMac20,1", model_code: "002K" },
+    ModelInfo { product_name: "iMac20,2", model_code: "002J" },
+    ModelInfo { product_name: "iMacPro1,1", model_code: "0DY2" },
+    ModelInfo { product_name: "MacBookPro15,1", model_code: "00P2" },
+    ModelInfo { product_name: "MacBookPro16,1", model_code: "0074" },
+    ModelInfo { product_name: "MacBookPro16,2", model_code: "00MV" },
+    ModelInfo { product_name: "MacBookPro16,3", model_code: "00MW" },
+    ModelInfo { product_name: "MacBookPro16,4", model_code: "0081" },
+    ModelInfo { product_name: "MacBookAir8,1", model_code: "00MN" },
+    ModelInfo { product_name: "MacBookAir9,1", model_code: "00KV" },
+    ModelInfo { product_name: "MacPro7,1", model_code: "0EJ6" },
+    ModelInfo { product_name: "Macmini8,1", model_code: "00L6" },
+    ModelInfo { product_name: "Macmini9,1", model_code: "00ZF" },
+];
+
+/// Stand-in code used for a `product_name` outside [`MODEL_INFO`]. Not a
+/// real Apple-assigned code for any specific model, but charset- and
+/// length-correct, so an uncommon `SystemProductName` still gets a
+/// structurally valid serial instead of aborting the run.
+const FALLBACK_MODEL_CODE: &str = "0000";
+
+/// A model/configuration code to generate a serial with for `product_name`:
+/// the one registered in [`MODEL_INFO`], or [`FALLBACK_MODEL_CODE`] if the
+/// product isn't listed. Apple assigns more than one legitimate
+/// configuration code per model, so this is just *a* valid code to generate
+/// with -- not the only code a real serial for this product could end in
+/// (see [`crate::serial::Serial::is_structurally_valid`], which doesn't
+/// require an exact match against it).
+pub fn model_code_for(product_name: &str) -> &'static str {
+    match MODEL_INFO.iter().find(|model| model.product_name == product_name) {
+        Some(model) => model.model_code,
+        None => {
+            log::warn!(
+                "No model/configuration code known for {product_name:?}; using fallback code {FALLBACK_MODEL_CODE:?}"
+            );
+            FALLBACK_MODEL_CODE
+        }
+    }
+}
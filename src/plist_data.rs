@@ -4,6 +4,9 @@ use plist::{Dictionary, Value};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::kernel_patches::{self, KernelVersion, PatchCategory};
+use crate::serial::Serial;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct MacPlist {
@@ -29,15 +32,40 @@ impl MacPlist {
         &self.platform_info.generic.mlb
     }
 
+    pub fn get_system_uuid(&self) -> &str {
+        &self.platform_info.generic.system_uuid
+    }
+
+    /// The stored ROM bytes, if `ROM` is present as `Value::Data`.
+    pub fn get_rom(&self) -> Option<&[u8]> {
+        match &self.platform_info.generic.rom {
+            Value::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+
     pub fn has_valid_serials(&self) -> bool {
-        let serial = self.get_serial_number();
+        let serial_number = self.get_serial_number();
         let mlb = self.get_mlb();
-        
+
         // Check if serials are not empty and not default values
-        !serial.is_empty() && 
-        !mlb.is_empty() && 
-        serial != "NO_DEVICE_SN" &&
-        mlb != "NO_LOGIC_BOARD_SN"
+        if serial_number.is_empty()
+            || mlb.is_empty()
+            || serial_number == "NO_DEVICE_SN"
+            || mlb == "NO_LOGIC_BOARD_SN"
+        {
+            return false;
+        }
+
+        // Reject obviously malformed serials (wrong length, or invalid
+        // characters in the prefix) so they trigger regeneration instead of
+        // being accepted. The model suffix is not compared against the
+        // product's configured code.
+        Serial {
+            serial_number: serial_number.to_string(),
+            board_serial: mlb.to_string(),
+        }
+        .is_structurally_valid(self.get_product_name())
     }
 
     pub fn set_serial_number(&mut self, serial_number: String) {
@@ -52,97 +80,125 @@ impl MacPlist {
         self.platform_info.generic.system_uuid = uuid.to_string();
     }
 
-    pub fn set_rom(&mut self, rom: [u8; 12]) {
-        self.platform_info.generic.rom = Value::Data(rom.to_vec());
+    /// Sets the ROM to `rom` verbatim. Real OpenCore configs typically store
+    /// a 6-byte `Generic -> ROM` (the Apple OUI plus a device-specific
+    /// suffix), but this accepts whatever length was generated or preserved
+    /// rather than assuming a fixed size.
+    pub fn set_rom(&mut self, rom: Vec<u8>) {
+        self.platform_info.generic.rom = Value::Data(rom);
     }
 
-    pub fn add_sequoia_kernel_patches(&mut self) {
-        // Get or create the Kernel section
-        let kernel = self.other
+    /// Applies every registered [`kernel_patches::KernelPatch`] in
+    /// `categories` that covers `target_kernel`, serializing each into the
+    /// `Kernel -> Patch` array exactly as OpenCore expects. Patches already
+    /// present (see [`patch_already_present`]) are skipped, so this is safe to
+    /// call repeatedly. An existing entry with the same `Comment` but a
+    /// non-matching `Find` does not count as present, and the patch is added
+    /// alongside it.
+    pub fn apply_patches(&mut self, target_kernel: &KernelVersion, categories: &[PatchCategory]) {
+        let kernel = self
+            .other
             .entry("Kernel".to_string())
             .or_insert_with(|| Value::Dictionary(Dictionary::new()));
 
-        if let Value::Dictionary(kernel_dict) = kernel {
-            // Get or create the Patch array
-            let patch_array = if let Some(existing) = kernel_dict.get("Patch") {
-                kernel_dict.get_mut("Patch").unwrap()
-            } else {
-                kernel_dict.insert("Patch".to_string(), Value::Array(Vec::new()));
-                kernel_dict.get_mut("Patch").unwrap()
-            };
-
-            if let Value::Array(patches) = patch_array {
-                // Check if patches already exist
-                let has_vmm_patch = patches.iter().any(|p| {
-                    if let Value::Dictionary(d) = p {
-                        if let Some(Value::String(comment)) = d.get("Comment") {
-                            return comment.contains("kern.hv_vmm_present") || 
-                                   comment.contains("VM detection");
-                        }
-                    }
-                    false
-                });
-
-                if !has_vmm_patch {
-                    // Patch 1: Rename kern.hv_vmm_present to hibernatecount
-                    let mut patch1 = Dictionary::new();
-                    patch1.insert("Arch".to_string(), Value::String("x86_64".to_string()));
-                    patch1.insert("Base".to_string(), Value::String("".to_string()));
-                    patch1.insert("Comment".to_string(), Value::String("Disable VM detection (kern.hv_vmm_present -> hibernatecount) for Sequoia".to_string()));
-                    patch1.insert("Count".to_string(), Value::Integer(1.into()));
-                    patch1.insert("Enabled".to_string(), Value::Boolean(true));
-                    patch1.insert("Find".to_string(), Value::Data(hex::decode("68696265726E61746568696472656164790068696265726E617465636F756E7400").unwrap()));
-                    patch1.insert("Replace".to_string(), Value::Data(hex::decode("68696265726E61746568696472656164790068765F766D6D5F70726573656E7400").unwrap()));
-                    patch1.insert("Identifier".to_string(), Value::String("kernel".to_string()));
-                    patch1.insert("MinKernel".to_string(), Value::String("24.0.0".to_string()));
-                    patch1.insert("MaxKernel".to_string(), Value::String("".to_string()));
-                    patch1.insert("Mask".to_string(), Value::Data(Vec::new()));
-                    patch1.insert("ReplaceMask".to_string(), Value::Data(Vec::new()));
-                    patch1.insert("Skip".to_string(), Value::Integer(0.into()));
-
-                    // Patch 2: Rename back (second patch)
-                    let mut patch2 = Dictionary::new();
-                    patch2.insert("Arch".to_string(), Value::String("x86_64".to_string()));
-                    patch2.insert("Base".to_string(), Value::String("".to_string()));
-                    patch2.insert("Comment".to_string(), Value::String("Disable VM detection (hibernatecount -> hv_vmm_present) for Sequoia".to_string()));
-                    patch2.insert("Count".to_string(), Value::Integer(1.into()));
-                    patch2.insert("Enabled".to_string(), Value::Boolean(true));
-                    patch2.insert("Find".to_string(), Value::Data(hex::decode("626F6F742073657373696F6E20555549440068765F766D6D5F70726573656E7400").unwrap()));
-                    patch2.insert("Replace".to_string(), Value::Data(hex::decode("626F6F742073657373696F6E20555549440068696265726E617465636F756E7400").unwrap()));
-                    patch2.insert("Identifier".to_string(), Value::String("kernel".to_string()));
-                    patch2.insert("MinKernel".to_string(), Value::String("24.0.0".to_string()));
-                    patch2.insert("MaxKernel".to_string(), Value::String("".to_string()));
-                    patch2.insert("Mask".to_string(), Value::Data(Vec::new()));
-                    patch2.insert("ReplaceMask".to_string(), Value::Data(Vec::new()));
-                    patch2.insert("Skip".to_string(), Value::Integer(0.into()));
-
-                    patches.push(Value::Dictionary(patch1));
-                    patches.push(Value::Dictionary(patch2));
-                    
-                    log::info!("Added Sequoia kernel patches for VM detection bypass");
+        let Value::Dictionary(kernel_dict) = kernel else {
+            return;
+        };
+
+        if !kernel_dict.contains_key("Patch") {
+            kernel_dict.insert("Patch".to_string(), Value::Array(Vec::new()));
+        }
+        let Some(Value::Array(patches)) = kernel_dict.get_mut("Patch") else {
+            return;
+        };
+
+        // Collected as owned values (not references into `patches`) so the
+        // lookup below doesn't keep `patches` borrowed while we push to it.
+        let existing: Vec<(String, Vec<u8>)> = patches
+            .iter()
+            .filter_map(|p| match p {
+                Value::Dictionary(d) => {
+                    let comment = match d.get("Comment") {
+                        Some(Value::String(c)) => c.clone(),
+                        _ => return None,
+                    };
+                    let find = match d.get("Find") {
+                        Some(Value::Data(f)) => f.clone(),
+                        _ => return None,
+                    };
+                    Some((comment, find))
                 }
+                _ => None,
+            })
+            .collect();
+
+        for patch in kernel_patches::patches_for(*target_kernel, categories) {
+            if existing
+                .iter()
+                .any(|(comment, find)| patch_already_present(comment, find, patch))
+            {
+                continue;
             }
+
+            let mut entry = Dictionary::new();
+            entry.insert("Arch".to_string(), Value::String(patch.arch.to_string()));
+            entry.insert("Base".to_string(), Value::String(String::new()));
+            entry.insert("Comment".to_string(), Value::String(patch.comment.to_string()));
+            entry.insert("Count".to_string(), Value::Integer(patch.count.into()));
+            entry.insert("Enabled".to_string(), Value::Boolean(true));
+            entry.insert("Find".to_string(), Value::Data(patch.find.to_vec()));
+            entry.insert("Replace".to_string(), Value::Data(patch.replace.to_vec()));
+            entry.insert("Identifier".to_string(), Value::String(patch.identifier.to_string()));
+            entry.insert("MinKernel".to_string(), Value::String(patch.min_kernel.to_string()));
+            entry.insert("MaxKernel".to_string(), Value::String(patch.max_kernel.to_string()));
+            entry.insert("Mask".to_string(), Value::Data(patch.mask.to_vec()));
+            entry.insert("ReplaceMask".to_string(), Value::Data(patch.replace_mask.to_vec()));
+            entry.insert("Skip".to_string(), Value::Integer(patch.skip.into()));
+
+            patches.push(Value::Dictionary(entry));
+            log::info!("Added kernel patch: {}", patch.comment);
         }
     }
 
-    pub fn has_sequoia_patches(&self) -> bool {
-        if let Some(Value::Dictionary(kernel_dict)) = self.other.get("Kernel") {
-            if let Some(Value::Array(patches)) = kernel_dict.get("Patch") {
-                return patches.iter().any(|p| {
-                    if let Value::Dictionary(d) = p {
-                        if let Some(Value::String(comment)) = d.get("Comment") {
-                            return comment.contains("kern.hv_vmm_present") || 
-                                   comment.contains("VM detection");
-                        }
+    /// Whether every patch in `categories` that covers `target_kernel` is
+    /// already present in the `Kernel -> Patch` array.
+    pub fn has_patches(&self, target_kernel: &KernelVersion, categories: &[PatchCategory]) -> bool {
+        let Some(Value::Dictionary(kernel_dict)) = self.other.get("Kernel") else {
+            return false;
+        };
+        let Some(Value::Array(patches)) = kernel_dict.get("Patch") else {
+            return false;
+        };
+
+        kernel_patches::patches_for(*target_kernel, categories)
+            .iter()
+            .all(|patch| {
+                patches.iter().any(|p| match p {
+                    Value::Dictionary(d) => {
+                        let Some(Value::String(comment)) = d.get("Comment") else {
+                            return false;
+                        };
+                        let Some(Value::Data(find)) = d.get("Find") else {
+                            return false;
+                        };
+                        patch_already_present(comment, find, patch)
                     }
-                    false
-                });
-            }
-        }
-        false
+                    _ => false,
+                })
+            })
     }
 }
 
+/// Whether a stored `Kernel -> Patch` entry with `comment` and `find` bytes
+/// is the serialized form of `patch`: same `Comment`, and `find` round-trips
+/// against `patch.find`/`patch.mask` via [`kernel_patches::mask_matches`].
+/// Checking `Find`/`Mask` too, not just `Comment`, is what makes the
+/// masked-match invariant meaningful when reading an already-applied patch
+/// back, not just when serializing one.
+fn patch_already_present(comment: &str, find: &[u8], patch: &kernel_patches::KernelPatch) -> bool {
+    comment == patch.comment && kernel_patches::mask_matches(find, patch.find, patch.mask)
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct PlatformInfo {
@@ -0,0 +1,120 @@
+//! Apple serial number generation and validation.
+//!
+//! A serial number ends with the 3- or 4-character model/configuration code
+//! for the target Mac (see [`crate::modelinfo`]), preceded by an 8-character
+//! prefix over Apple's uppercase-alnum alphabet (which skips characters that
+//! are easy to misread: `0`, `1`, `I`, `O`): a 1-character manufacturing-plant
+//! code, a 2-character year/week-of-manufacture code, and a 5-character
+//! per-unit unique identifier. The combined string is always 12 characters
+//! for a 4-character model code, or 11 for a 3-character one. The model code
+//! itself is an Apple-assigned value and isn't restricted to that alphabet
+//! (it routinely contains `0`).
+
+use anyhow::{bail, Context, Result};
+use rand::seq::IndexedRandom;
+
+use crate::modelinfo;
+
+/// Characters Apple uses in the generated portion of a serial number:
+/// uppercase letters and digits, excluding `0`, `1`, `I`, and `O`.
+pub const SERIAL_ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+/// Manufacturing-plant codes used for the first character of a generated
+/// serial's prefix. A representative subset, like [`modelinfo::MODEL_INFO`],
+/// not Apple's full list of plant codes.
+const LOCATION_CODES: &[u8] = b"FC45QRVXY";
+
+pub struct Serial {
+    pub serial_number: String,
+    pub board_serial: String,
+}
+
+impl Serial {
+    /// Checks length and the charset of the generated prefix, against the
+    /// length of `product_name`'s configured code (or the common
+    /// 4-character length, for a product outside [`modelinfo::MODEL_INFO`]).
+    /// This deliberately does *not* require the serial's suffix to match
+    /// that one specific code: Apple assigns more than one legitimate
+    /// configuration code per model, so a real serial ending in a different
+    /// code of the right length is not malformed. This catches obviously
+    /// malformed serials (wrong length, lowercase/invalid characters in the
+    /// prefix) without trying to validate Apple's actual allocation data.
+    pub fn is_structurally_valid(&self, product_name: &str) -> bool {
+        let code_len = modelinfo::model_code_for(product_name).len();
+        let Some(expected_len) = serial_length_for(code_len) else {
+            return false;
+        };
+
+        if self.serial_number.len() != expected_len {
+            return false;
+        }
+
+        let prefix_len = expected_len - code_len;
+        self.serial_number[..prefix_len]
+            .bytes()
+            .all(|byte| SERIAL_ALPHABET.contains(&byte))
+    }
+}
+
+/// Total serial length for a given model-code length: 12 characters for a
+/// 4-character code, 11 for a 3-character one. The prefix preceding the
+/// model code is always 8 characters.
+fn serial_length_for(code_len: usize) -> Option<usize> {
+    match code_len {
+        3 => Some(11),
+        4 => Some(12),
+        _ => None,
+    }
+}
+
+/// Builds a structurally valid serial number and board serial (MLB) for
+/// `product_name`.
+pub fn find_desired(product_name: &str) -> Result<Serial> {
+    let model_code = modelinfo::model_code_for(product_name);
+    let total_len = serial_length_for(model_code.len())
+        .with_context(|| format!("Model code {model_code:?} has an unexpected length"))?;
+    let prefix_len = total_len - model_code.len();
+
+    let mut rng = rand::rng();
+    let mut serial_number = String::with_capacity(total_len);
+
+    // Manufacturing-plant code.
+    let location = *LOCATION_CODES
+        .choose(&mut rng)
+        .context("Location code alphabet should never be empty")?;
+    serial_number.push(location as char);
+
+    // Year/week-of-manufacture code.
+    for _ in 0..2 {
+        let byte = *SERIAL_ALPHABET
+            .choose(&mut rng)
+            .context("Serial alphabet should never be empty")?;
+        serial_number.push(byte as char);
+    }
+
+    // Per-unit unique identifier filling out the rest of the prefix.
+    for _ in 0..(prefix_len - 3) {
+        let byte = *SERIAL_ALPHABET
+            .choose(&mut rng)
+            .context("Serial alphabet should never be empty")?;
+        serial_number.push(byte as char);
+    }
+
+    serial_number.push_str(model_code);
+
+    // The board serial (MLB) doesn't carry a model suffix; it's a 17-character
+    // string over the same alphabet.
+    let mut board_serial = String::with_capacity(17);
+    for _ in 0..17 {
+        let byte = *SERIAL_ALPHABET
+            .choose(&mut rng)
+            .context("Serial alphabet should never be empty")?;
+        board_serial.push(byte as char);
+    }
+
+    let serial = Serial { serial_number, board_serial };
+    if !serial.is_structurally_valid(product_name) {
+        bail!("Generated serial failed structural validation for {product_name:?}");
+    }
+    Ok(serial)
+}